@@ -1,11 +1,145 @@
+use blake3::Hasher as Blake3Hasher;
 use bzip2::read::BzDecoder;
 use crc32fast::Hasher;
-use memmap2::{Advice, Mmap, MmapMut};
+use memmap2::{Mmap, MmapMut};
 use pyo3::prelude::*;
 use qbsdiff::Bspatch;
+use rayon::prelude::*;
 use std::fs::File;
 use std::io::{self, Read};
-use std::os::unix::io::FromRawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::PathBuf;
+
+/// Minimum run length worth moving with `copy_file_range`/`splice` instead
+/// of just writing the bytes into the target mmap. Below this, the syscall
+/// overhead isn't worth it.
+const INPLACE_COPY_THRESHOLD: usize = 4096;
+
+/// Copy `len` bytes from `(src_fd, src_off)` to `(dst_fd, dst_off)` kernel-side
+/// with `copy_file_range`, falling back to `splice` through a pipe, and
+/// finally to a user-space read/write if neither syscall is available.
+fn copy_range_kernel_side(
+    src_fd: i32,
+    src_off: i64,
+    dst_fd: i32,
+    dst_off: i64,
+    len: usize,
+) -> io::Result<usize> {
+    let mut src_off_mut = src_off;
+    let mut dst_off_mut = dst_off;
+    let ret = unsafe {
+        libc::copy_file_range(
+            src_fd,
+            &mut src_off_mut,
+            dst_fd,
+            &mut dst_off_mut,
+            len,
+            0,
+        )
+    };
+    if ret >= 0 {
+        return Ok(ret as usize);
+    }
+
+    match io::Error::last_os_error().raw_os_error() {
+        // Not supported on this filesystem/kernel, or crossing devices:
+        // fall back to splice, then to a plain user-space copy
+        Some(libc::ENOSYS) | Some(libc::EXDEV) | Some(libc::EOPNOTSUPP) => {
+            splice_kernel_side(src_fd, src_off, dst_fd, dst_off, len)
+        }
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn splice_kernel_side(
+    src_fd: i32,
+    src_off: i64,
+    dst_fd: i32,
+    dst_off: i64,
+    len: usize,
+) -> io::Result<usize> {
+    let mut pipe_fds = [0i32; 2];
+    if unsafe { libc::pipe(pipe_fds.as_mut_ptr()) } != 0 {
+        return copy_range_user_space(src_fd, src_off, dst_fd, dst_off, len);
+    }
+    let (read_end, write_end) = (pipe_fds[0], pipe_fds[1]);
+
+    let mut src_off_mut = src_off;
+    let mut dst_off_mut = dst_off;
+    let to_pipe = unsafe {
+        libc::splice(
+            src_fd,
+            &mut src_off_mut,
+            write_end,
+            std::ptr::null_mut(),
+            len,
+            libc::SPLICE_F_MOVE,
+        )
+    };
+    let result = if to_pipe <= 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        let from_pipe = unsafe {
+            libc::splice(
+                read_end,
+                std::ptr::null_mut(),
+                dst_fd,
+                &mut dst_off_mut,
+                to_pipe as usize,
+                libc::SPLICE_F_MOVE,
+            )
+        };
+        if from_pipe < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(from_pipe as usize)
+        }
+    };
+
+    unsafe {
+        libc::close(read_end);
+        libc::close(write_end);
+    }
+
+    match result {
+        Ok(n) => Ok(n),
+        Err(e) => match e.raw_os_error() {
+            Some(libc::ENOSYS) | Some(libc::EINVAL) => {
+                copy_range_user_space(src_fd, src_off, dst_fd, dst_off, len)
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+/// Last-resort fallback: read the range into a buffer and write it back out.
+fn copy_range_user_space(
+    src_fd: i32,
+    src_off: i64,
+    dst_fd: i32,
+    dst_off: i64,
+    len: usize,
+) -> io::Result<usize> {
+    let mut buf = vec![0u8; len];
+    let n = unsafe {
+        libc::pread(src_fd, buf.as_mut_ptr() as *mut libc::c_void, len, src_off)
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let n = unsafe {
+        libc::pwrite(
+            dst_fd,
+            buf.as_ptr() as *const libc::c_void,
+            n as usize,
+            dst_off,
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
 
 /// Given a binary patch, update a file in-place
 ///
@@ -35,7 +169,7 @@ fn bspatch_rs(py: Python<'_>, source: i32, patch: &[u8]) -> io::Result<Vec<u8>>
         // partial update in Rust and ensures mutual exclusivity of file access
         let mut mmap = unsafe {
             MmapMut::map_mut(&file).map_err(|e| {
-                io::Error::new(io::ErrorKind::Other, format!("Failed to map source: {}", e))
+                io::Error::other(format!("Failed to map source: {}", e))
             })?
         };
 
@@ -69,6 +203,162 @@ fn bspatch_rs(py: Python<'_>, source: i32, patch: &[u8]) -> io::Result<Vec<u8>>
     })
 }
 
+/// Like `bspatch_rs`, but takes the path to the file to patch rather than a
+/// caller-owned file descriptor.
+///
+/// `bspatch_rs`, `bz2_decompress_rs`, `crc32_rs` and `crc32_mmap_rs` each
+/// reconstruct a `File` from a raw fd with
+/// `from_raw_fd` and then `mem::forget` it to avoid closing a descriptor
+/// we don't own — a pattern that duplicates unsafe code and risks a
+/// double-close or leak if an early `?` return is ever added. Taking a
+/// path instead keeps ownership of the `File` entirely in Rust, so it's
+/// closed correctly via its destructor on every return path, including
+/// errors.
+#[pyfunction]
+fn bspatch_path_rs(py: Python<'_>, source: PathBuf, patch: &[u8]) -> io::Result<Vec<u8>> {
+    py.allow_threads(|| {
+        let patcher = Bspatch::new(patch)?;
+
+        let file = File::options().read(true).write(true).open(&source)?;
+        let original_size = file.metadata()?.len();
+
+        if original_size < patcher.hint_target_size() {
+            file.set_len(patcher.hint_target_size())?;
+        }
+
+        // See https://docs.rs/memmap2/0.9.5/memmap2/struct.MmapMut.html#safety
+        // In context of applying partial updates, umu-launcher mitigates this
+        // risk by holding a lock in Python's context before applying the
+        // partial update in Rust and ensures mutual exclusivity of file access
+        let mut mmap = unsafe {
+            MmapMut::map_mut(&file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let mut target = Vec::with_capacity(patcher.hint_target_size() as usize);
+        patcher.apply(&mmap[..original_size as usize], &mut target)?;
+
+        if target.len() > mmap.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Patch exceeds mapped file size",
+            ));
+        }
+        mmap[..target.len()].copy_from_slice(&target[..]);
+
+        if target.len() < original_size as usize {
+            file.set_len(target.len() as u64)?;
+        }
+        mmap.flush_async()?;
+        Ok(target)
+    })
+}
+
+/// Like `bspatch_rs`, but applies the patch between two separate
+/// descriptors — an unmodified `source` and the `target` to write the
+/// patched result into.
+///
+/// `qbsdiff::Bspatch::apply` only writes to a generic `Write` sink, so the
+/// full patched output is still materialized into an in-memory buffer
+/// first, the same as `bspatch_rs`; this doesn't avoid that allocation.
+/// What it avoids is the *second* full-size copy: after applying, any
+/// byte-for-byte-unchanged run between the buffer and `source` at the
+/// same offset is moved straight from `source` to `target` with
+/// `copy_file_range` (kernel-side) instead of being written into
+/// `target`'s mapping, halving memory traffic for the ranges that qualify.
+/// This is a post-hoc comparison against `source`, not an inspection of
+/// the patch's BSDIFF control blocks, so a diff/extra segment whose output
+/// happens to coincide with `source` is also (harmlessly) treated as a
+/// copy.
+///
+/// Returns `(bytes_fast_path, bytes_direct)`: the number of bytes moved
+/// via `copy_file_range`/`splice` versus written directly into `target`'s
+/// mapping.
+#[pyfunction]
+fn bspatch_inplace_rs(py: Python<'_>, source: i32, target: i32, patch: &[u8]) -> io::Result<(u64, u64)> {
+    py.allow_threads(|| {
+        let patcher = Bspatch::new(patch)?;
+
+        // Wrapped in `ManuallyDrop` rather than `mem::forget`'d at the end
+        // of the function: every step below is fallible and can return
+        // early via `?`, and Python still owns these descriptors, so
+        // nothing here may ever run their destructor, not just the
+        // success path. See bspatch_rs for rationale on the use of unsafe.
+        let source_file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(source) });
+        let target_file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(target) });
+
+        let source_mmap = unsafe {
+            Mmap::map(&*source_file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let hint_size = patcher.hint_target_size();
+        target_file.set_len(hint_size)?;
+        let mut target_mmap = unsafe {
+            MmapMut::map_mut(&*target_file).map_err(|e| {
+                io::Error::other(format!("Failed to map target: {}", e))
+            })?
+        };
+
+        let mut patched = Vec::with_capacity(hint_size as usize);
+        patcher.apply(&source_mmap[..], &mut patched)?;
+
+        if patched.len() as u64 > hint_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Patch exceeds mapped target size",
+            ));
+        }
+
+        let source_fd = source_file.as_raw_fd();
+        let target_fd = target_file.as_raw_fd();
+        let (mut fast_path_bytes, mut direct_bytes) = (0u64, 0u64);
+
+        // Scan for runs where the patched output is identical to the
+        // source at the same offset, whichever BSDIFF segment produced
+        // them, and move those ranges with the kernel instead of touching
+        // the mapping
+        let len = patched.len();
+        let mut i = 0;
+        while i < len {
+            let unchanged = i < source_mmap.len() && patched[i] == source_mmap[i];
+            let mut j = i + 1;
+            while j < len && j < source_mmap.len() && (patched[j] == source_mmap[j]) == unchanged {
+                j += 1;
+            }
+            let run_len = j - i;
+
+            if unchanged && run_len >= INPLACE_COPY_THRESHOLD {
+                let moved = copy_range_kernel_side(source_fd, i as i64, target_fd, i as i64, run_len)?;
+                fast_path_bytes += moved as u64;
+                if moved < run_len {
+                    target_mmap[i + moved..j].copy_from_slice(&patched[i + moved..j]);
+                    direct_bytes += (run_len - moved) as u64;
+                }
+            } else {
+                target_mmap[i..j].copy_from_slice(&patched[i..j]);
+                direct_bytes += run_len as u64;
+            }
+
+            i = j;
+        }
+
+        target_mmap.flush_async()?;
+
+        // `hint_target_size` is just the patch header's declared size;
+        // the actual patched output can come out shorter, so shrink the
+        // target to match (see the "Handle small file case" step in
+        // bspatch_rs/bspatch_path_rs)
+        if (patched.len() as u64) < hint_size {
+            target_file.set_len(patched.len() as u64)?;
+        }
+
+        Ok((fast_path_bytes, direct_bytes))
+    })
+}
+
 #[pyfunction]
 fn bz2_decompress_rs(py: Python<'_>, source: &[u8], target: i32, size: u64) -> io::Result<u64> {
     py.allow_threads(|| {
@@ -78,7 +368,23 @@ fn bz2_decompress_rs(py: Python<'_>, source: &[u8], target: i32, size: u64) -> i
         let mut decoder = BzDecoder::new(source);
         let result = io::copy(&mut decoder, &mut file);
         std::mem::forget(file);
-        return result;
+        result
+    })
+}
+
+/// Like `bz2_decompress_rs`, but takes the path of the file to decompress
+/// into rather than a caller-owned file descriptor (see `bspatch_path_rs`).
+#[pyfunction]
+fn bz2_decompress_path_rs(py: Python<'_>, source: &[u8], target: PathBuf, size: u64) -> io::Result<u64> {
+    py.allow_threads(|| {
+        let mut file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&target)?;
+        file.set_len(size)?;
+        let mut decoder = BzDecoder::new(source);
+        io::copy(&mut decoder, &mut file)
     })
 }
 
@@ -96,6 +402,20 @@ fn crc32_rs(py: Python<'_>, source: i32) -> io::Result<u32> {
     })
 }
 
+/// Like `crc32_rs`, but takes the path of the file to hash rather than a
+/// caller-owned file descriptor (see `bspatch_path_rs`).
+#[pyfunction]
+fn crc32_path_rs(py: Python<'_>, source: PathBuf) -> io::Result<u32> {
+    py.allow_threads(|| {
+        let mut file = File::open(&source)?;
+        let mut hasher = Hasher::new();
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+        Ok(hasher.finalize())
+    })
+}
+
 #[pyfunction]
 fn crc32_mmap_rs(py: Python<'_>, source: i32) -> u32 {
     py.allow_threads(|| {
@@ -112,11 +432,455 @@ fn crc32_mmap_rs(py: Python<'_>, source: i32) -> u32 {
     })
 }
 
+/// Like `crc32_mmap_rs`, but takes the path of the file to hash rather
+/// than a caller-owned file descriptor (see `bspatch_path_rs`). Unlike
+/// `crc32_mmap_rs`, mapping failures are reported instead of silently
+/// hashed as empty, since there's no fd-lifetime juggling left to excuse
+/// swallowing the error.
+#[pyfunction]
+fn crc32_mmap_path_rs(py: Python<'_>, source: PathBuf) -> io::Result<u32> {
+    py.allow_threads(|| {
+        let file = File::open(&source)?;
+        let mmap = unsafe {
+            Mmap::map(&file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let mut hasher = Hasher::new();
+        hasher.update(&mmap[..]);
+        Ok(hasher.finalize())
+    })
+}
+
+/// Hash a file with BLAKE3, memory-mapping it and hashing with BLAKE3's
+/// parallel path so large files are split into independently-hashed
+/// subtrees whose chaining values are combined by rayon.
+///
+/// Unlike CRC32, BLAKE3 is a cryptographic hash, making this suitable for
+/// verifying a delta-patched file against an expected content hash rather
+/// than just detecting accidental corruption.
+#[pyfunction]
+fn blake3_mmap_rs(py: Python<'_>, source: i32) -> io::Result<[u8; 32]> {
+    py.allow_threads(|| {
+        // Wrapped in `ManuallyDrop` rather than `mem::forget`'d at the end
+        // of the function, since `Mmap::map` below is fallible and Python
+        // still owns this descriptor — see bspatch_inplace_rs.
+        let file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(source) });
+        let mmap = unsafe {
+            Mmap::map(&*file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let mut hasher = Blake3Hasher::new();
+        hasher.update_rayon(&mmap[..]);
+        Ok(*hasher.finalize().as_bytes())
+    })
+}
+
+/// Hash a file with BLAKE3 by streaming its contents rather than
+/// memory-mapping it, for descriptors that can't be mapped (pipes,
+/// sockets, and other non-regular files).
+#[pyfunction]
+fn blake3_reader_rs(py: Python<'_>, source: i32) -> io::Result<[u8; 32]> {
+    py.allow_threads(|| {
+        // Wrapped in `ManuallyDrop` rather than `mem::forget`'d at the end
+        // of the function, since `io::copy` below is fallible and Python
+        // still owns this descriptor — see bspatch_inplace_rs.
+        let mut file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(source) });
+        let mut hasher = Blake3Hasher::new();
+        io::copy(&mut *file, &mut hasher)?;
+        Ok(*hasher.finalize().as_bytes())
+    })
+}
+
+/// Hash a file with BLAKE3 given its path rather than a caller-owned file
+/// descriptor.
+///
+/// Reconstructing a `File`/`Mmap` from a raw fd we don't own is
+/// fundamentally unsafe (see bspatch_rs). Taking a path instead lets us
+/// open and map the file entirely within Rust, so there's no unsafe
+/// reconstruction and the `File` closes correctly via its destructor.
+#[pyfunction]
+fn blake3_mmap_path_rs(py: Python<'_>, source: PathBuf) -> io::Result<[u8; 32]> {
+    py.allow_threads(|| {
+        let mut hasher = Blake3Hasher::new();
+        hasher.update_mmap_rayon(&source)?;
+        Ok(*hasher.finalize().as_bytes())
+    })
+}
+
+// Width of the GF(2) matrices used by `crc32_combine`, one bit per matrix row
+const GF2_DIM: usize = 32;
+
+/// Apply a GF(2) matrix (encoded as one `u32` bitmask per row) to a vector.
+fn gf2_matrix_times(mat: &[u32; GF2_DIM], mut vec: u32) -> u32 {
+    let mut sum = 0u32;
+    let mut n = 0;
+    while vec != 0 {
+        if vec & 1 != 0 {
+            sum ^= mat[n];
+        }
+        vec >>= 1;
+        n += 1;
+    }
+    sum
+}
+
+/// Square a GF(2) matrix, i.e. compose the operator it represents with
+/// itself, so applying it advances a CRC by twice as many zero bits/bytes.
+fn gf2_matrix_square(square: &mut [u32; GF2_DIM], mat: &[u32; GF2_DIM]) {
+    for n in 0..GF2_DIM {
+        square[n] = gf2_matrix_times(mat, mat[n]);
+    }
+}
+
+/// Combine two independently-computed CRC32 values as though `crc_b` had
+/// been computed by continuing `crc_a`'s checksum over `len_b` more bytes,
+/// using the standard CRC-combine algorithm (the same one zlib's
+/// `crc32_combine` uses).
+///
+/// CRC32 is linear over GF(2), so "advance a CRC register past one zero
+/// bit" can be represented as a 32x32 bit matrix. Squaring that matrix
+/// three times (bit -> 2 bits -> 4 bits -> 1 byte) gives "advance past one
+/// zero byte", and raising that byte-advance operator to the `len_b` power
+/// via repeated squaring gives "advance past `len_b` zero bytes". Applying
+/// that to `crc_a` and XORing with `crc_b` yields the CRC of the
+/// concatenation.
+fn crc32_combine(crc_a: u32, crc_b: u32, len_b: u64) -> u32 {
+    if len_b == 0 {
+        return crc_a;
+    }
+
+    // `odd` starts as the "advance past one zero bit" operator
+    let mut odd = [0u32; GF2_DIM];
+    odd[0] = 0xedb88320;
+    let mut row = 1u32;
+    for slot in odd.iter_mut().skip(1) {
+        *slot = row;
+        row <<= 1;
+    }
+
+    let mut even = [0u32; GF2_DIM];
+    gf2_matrix_square(&mut even, &odd); // advance past two zero bits
+    gf2_matrix_square(&mut odd, &even); // advance past four zero bits (half a byte)
+
+    let mut crc = crc_a;
+    let mut len = len_b;
+    loop {
+        gf2_matrix_square(&mut even, &odd); // one zero byte on the first pass; each pass doubles it
+        if len & 1 != 0 {
+            crc = gf2_matrix_times(&even, crc);
+        }
+        len >>= 1;
+        if len == 0 {
+            break;
+        }
+
+        gf2_matrix_square(&mut odd, &even); // two zero bytes on the first pass; each pass doubles it
+        if len & 1 != 0 {
+            crc = gf2_matrix_times(&odd, crc);
+        }
+        len >>= 1;
+        if len == 0 {
+            break;
+        }
+    }
+
+    crc ^ crc_b
+}
+
+#[cfg(test)]
+mod crc32_combine_tests {
+    use super::*;
+
+    fn serial_crc32(data: &[u8]) -> u32 {
+        let mut hasher = Hasher::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    #[test]
+    fn combine_matches_serial_crc32_across_chunk_counts() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let expected = serial_crc32(&data);
+
+        for chunk_count in [1usize, 2, 3, 7, 16, 100, 10_000] {
+            let chunk_size = (data.len() / chunk_count).max(1);
+            let mut combined = (0u32, 0u64);
+            for chunk in data.chunks(chunk_size) {
+                let crc = serial_crc32(chunk);
+                combined = (
+                    crc32_combine(combined.0, crc, chunk.len() as u64),
+                    combined.1 + chunk.len() as u64,
+                );
+            }
+            assert_eq!(combined.0, expected, "chunk_count={}", chunk_count);
+        }
+    }
+
+    #[test]
+    fn combine_handles_empty_input() {
+        let empty_crc = serial_crc32(&[]);
+        assert_eq!(crc32_combine(empty_crc, empty_crc, 0), empty_crc);
+    }
+}
+
+/// Like `crc32_mmap_rs`, but splits the mapped file into contiguous chunks
+/// and hashes each chunk concurrently with rayon, folding the per-chunk
+/// CRC32s back together with `crc32_combine`. The result is bit-identical
+/// to `crc32_mmap_rs` regardless of how many chunks the file is split
+/// into, since CRC32 combination is exact.
+#[pyfunction]
+fn crc32_par_rs(py: Python<'_>, source: i32) -> io::Result<u32> {
+    py.allow_threads(|| {
+        // Wrapped in `ManuallyDrop` rather than `mem::forget`'d at the end
+        // of the function, since `Mmap::map` below is fallible and Python
+        // still owns this descriptor — see bspatch_inplace_rs.
+        let file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(source) });
+        let mmap = unsafe {
+            Mmap::map(&*file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let chunk_count = rayon::current_num_threads().max(1);
+        let chunk_size = (mmap.len() / chunk_count).max(1);
+        let (crc, _) = mmap
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut hasher = Hasher::new();
+                hasher.update(chunk);
+                (hasher.finalize(), chunk.len() as u64)
+            })
+            .reduce(
+                || (0u32, 0u64),
+                |(crc_a, len_a), (crc_b, len_b)| {
+                    (crc32_combine(crc_a, crc_b, len_b), len_a + len_b)
+                },
+            );
+
+        Ok(crc)
+    })
+}
+
+/// Error returned by `apply_delta_rs`: either an I/O failure while
+/// decompressing/applying the patch, or a checksum mismatch once applied
+/// (in which case the original file contents have already been restored).
+#[derive(Debug)]
+enum DeltaError {
+    Io(io::Error),
+    ChecksumMismatch(String),
+}
+
+impl std::fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeltaError::Io(e) => write!(f, "{}", e),
+            DeltaError::ChecksumMismatch(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
+impl From<io::Error> for DeltaError {
+    fn from(e: io::Error) -> Self {
+        DeltaError::Io(e)
+    }
+}
+
+impl From<DeltaError> for PyErr {
+    fn from(e: DeltaError) -> Self {
+        match e {
+            DeltaError::Io(e) => e.into(),
+            DeltaError::ChecksumMismatch(msg) => pyo3::exceptions::PyValueError::new_err(msg),
+        }
+    }
+}
+
+/// Record `(offset, old_bytes)` for each contiguous run where `new` differs
+/// from `old` at the same offset, so a failed verification can undo
+/// exactly those ranges without buffering the whole original file.
+fn snapshot_changed_ranges(old: &[u8], new: &[u8]) -> Vec<(usize, Vec<u8>)> {
+    let mut snapshot = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        if old[i] == new[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < new.len() && old[i] != new[i] {
+            i += 1;
+        }
+        snapshot.push((start, old[start..i].to_vec()));
+    }
+    snapshot
+}
+
+/// Undo exactly the ranges recorded by `snapshot_changed_ranges`, restoring
+/// `buf` to its pre-patch contents.
+fn restore_snapshot(buf: &mut [u8], snapshot: &[(usize, Vec<u8>)]) {
+    for (offset, bytes) in snapshot {
+        buf[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+#[cfg(test)]
+mod snapshot_rollback_tests {
+    use super::*;
+
+    #[test]
+    fn restore_snapshot_undoes_exactly_the_changed_ranges() {
+        let original = b"the quick brown fox jumps over the lazy dog".to_vec();
+        let patched = b"the slow brown cat jumps over the busy dog!".to_vec();
+        assert_eq!(original.len(), patched.len());
+
+        let snapshot = snapshot_changed_ranges(&original, &patched);
+
+        let mut buf = patched.clone();
+        restore_snapshot(&mut buf, &snapshot);
+        assert_eq!(buf, original);
+    }
+
+    #[test]
+    fn snapshot_is_empty_when_nothing_changes() {
+        let data = b"unchanged".to_vec();
+        assert!(snapshot_changed_ranges(&data, &data).is_empty());
+    }
+}
+
+/// Decompress (if needed), apply, and verify a delta patch as a single
+/// operation. A failed verification is rolled back; a crash or kill after
+/// the patched bytes are written into `source`'s mapping and before
+/// verification or rollback finishes leaves `source` fully or partially
+/// patched, since no journal of the changes is written to disk.
+///
+/// Previously callers had to separately invoke `bz2_decompress_rs`,
+/// `bspatch_rs`, and `crc32_rs`/`blake3_mmap_rs`, coordinating fds and
+/// locking in Python with no atomic guarantee that a half-applied patch
+/// was detected. This decompresses `patch` if it's bzip2-compressed rather
+/// than a raw BSDIFF40 payload, applies it in-place over `source`, and
+/// only commits once the result matches whichever of `expected_crc32`/
+/// `expected_blake3` were supplied — otherwise the original contents are
+/// restored and a `DeltaError::ChecksumMismatch` is returned.
+///
+/// To make rollback cheap without buffering the whole original file, only
+/// the byte ranges the patch actually changes are snapshotted before
+/// they're overwritten, so a failed verification can undo exactly those
+/// ranges.
+///
+/// Returns the actual `(crc32, blake3)` of the committed result.
+#[pyfunction]
+#[pyo3(signature = (source, patch, expected_crc32=None, expected_blake3=None))]
+fn apply_delta_rs(
+    py: Python<'_>,
+    source: i32,
+    patch: &[u8],
+    expected_crc32: Option<u32>,
+    expected_blake3: Option<[u8; 32]>,
+) -> Result<(u32, [u8; 32]), DeltaError> {
+    py.allow_threads(|| {
+        // BSDIFF 4.x patches carry the "BSDIFF40" magic; anything else is
+        // assumed to be the patch bzip2-compressed on top of that
+        let decompressed;
+        let patch_bytes: &[u8] = if patch.starts_with(b"BSDIFF40") {
+            patch
+        } else {
+            let mut buf = Vec::new();
+            BzDecoder::new(patch).read_to_end(&mut buf)?;
+            decompressed = buf;
+            &decompressed
+        };
+
+        let patcher = Bspatch::new(patch_bytes)?;
+
+        // Wrapped once in `ManuallyDrop` for the whole function rather
+        // than `mem::forget`'d after each use: every step below that
+        // touches `file` — `metadata`, `set_len`, `MmapMut::map_mut`, and
+        // the rollback/shrink `set_len` calls further down — is fallible,
+        // and Python still owns this descriptor, so none of them may ever
+        // run its destructor on an early return. See bspatch_inplace_rs.
+        let file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(source) });
+        let original_size = file.metadata()?.len();
+
+        if original_size < patcher.hint_target_size() {
+            file.set_len(patcher.hint_target_size())?;
+        }
+
+        let mut mmap = unsafe {
+            MmapMut::map_mut(&*file).map_err(|e| {
+                io::Error::other(format!("Failed to map source: {}", e))
+            })?
+        };
+
+        let mut target = Vec::with_capacity(patcher.hint_target_size() as usize);
+        patcher.apply(&mmap[..original_size as usize], &mut target)?;
+
+        if target.len() > mmap.len() {
+            return Err(DeltaError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Patch exceeds mapped file size",
+            )));
+        }
+
+        // Snapshot only the ranges the patch is about to change
+        let snapshot = snapshot_changed_ranges(&mmap[..target.len()], &target);
+
+        mmap[..target.len()].copy_from_slice(&target[..]);
+
+        let actual_crc32 = {
+            let mut hasher = Hasher::new();
+            hasher.update(&mmap[..target.len()]);
+            hasher.finalize()
+        };
+        let actual_blake3 = {
+            let mut hasher = Blake3Hasher::new();
+            hasher.update_rayon(&mmap[..target.len()]);
+            *hasher.finalize().as_bytes()
+        };
+
+        let crc32_ok = expected_crc32.is_none_or(|e| e == actual_crc32);
+        let blake3_ok = expected_blake3.is_none_or(|e| e == actual_blake3);
+
+        if !crc32_ok || !blake3_ok {
+            restore_snapshot(&mut mmap[..target.len()], &snapshot);
+            mmap.flush()?;
+            if target.len() != original_size as usize {
+                file.set_len(original_size)?;
+            }
+            return Err(DeltaError::ChecksumMismatch(format!(
+                "apply_delta_rs: verification failed (crc32 ok={}, blake3 ok={})",
+                crc32_ok, blake3_ok
+            )));
+        }
+
+        if target.len() < original_size as usize {
+            file.set_len(target.len() as u64)?;
+        }
+        mmap.flush_async()?;
+
+        Ok((actual_crc32, actual_blake3))
+    })
+}
+
 #[pymodule(name = "umu_delta")]
 fn umu(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(bspatch_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(bspatch_path_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(bspatch_inplace_rs, m)?)?;
     m.add_function(wrap_pyfunction!(crc32_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(crc32_path_rs, m)?)?;
     m.add_function(wrap_pyfunction!(bz2_decompress_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(bz2_decompress_path_rs, m)?)?;
     m.add_function(wrap_pyfunction!(crc32_mmap_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(crc32_mmap_path_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(crc32_par_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(blake3_mmap_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(blake3_reader_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(blake3_mmap_path_rs, m)?)?;
+    m.add_function(wrap_pyfunction!(apply_delta_rs, m)?)?;
     Ok(())
 }